@@ -0,0 +1,209 @@
+//! Cargo features and `#[cfg]` gates for optional inlined modules.
+//!
+//! Mirrors the pattern already used for capabilities like `json`, `msgpack`
+//! and `serve`: a workspace crate or module marked optional becomes a named
+//! Cargo feature that gates the module itself, every `pub use`/`use` site
+//! that reaches into it, and the external deps it alone needs. Modules that
+//! only exist to support an optional module (private helpers with no other
+//! caller) are pulled under the same feature so disabling it drops them too.
+
+use std::collections::HashMap;
+
+use quote::ToTokens;
+use syn::{Attribute, Item};
+
+use crate::reachability::CanonicalPath;
+
+/// One module the caller has marked optional, plus what it needs to compile.
+#[derive(Debug, Clone)]
+pub struct OptionalModule {
+    pub module_path: CanonicalPath,
+    pub feature_name: String,
+    /// External crate deps this module alone requires, e.g. `["serde"]`.
+    pub external_deps: Vec<String>,
+    /// Private modules that exist only to support this one; they ride
+    /// along under the same feature rather than getting their own.
+    pub support_modules: Vec<CanonicalPath>,
+}
+
+/// The full set of optional modules for one subset.
+#[derive(Debug, Default)]
+pub struct FeaturePlan {
+    pub optional: Vec<OptionalModule>,
+}
+
+impl FeaturePlan {
+    /// Maps every gated module path -- both the ones the caller named
+    /// directly and their pulled-in support modules -- to the feature that
+    /// gates it. A support module shared between two optional modules would
+    /// be ambiguous; the resolver takes the first feature that claims it
+    /// and that's the caller's bug to fix upstream, not something to guess
+    /// around here.
+    pub fn module_features(&self) -> HashMap<CanonicalPath, String> {
+        let mut map = HashMap::new();
+        for module in &self.optional {
+            map.entry(module.module_path.clone())
+                .or_insert_with(|| module.feature_name.clone());
+            for support in &module.support_modules {
+                map.entry(support.clone())
+                    .or_insert_with(|| module.feature_name.clone());
+            }
+        }
+        map
+    }
+}
+
+/// Attaches `#[cfg(feature = "name")]` to an inlined module item.
+pub fn gate_module(item: &mut Item, feature: &str) {
+    if let Item::Mod(module) = item {
+        push_cfg_attr(&mut module.attrs, feature);
+    }
+}
+
+/// Walks a slice of items (typically a crate root's item list) and attaches
+/// the matching `#[cfg(feature = "...")]` to every `use`/`pub use` item
+/// whose path starts with a gated module, so nothing references a module
+/// that might not exist once its feature is off.
+pub fn propagate_cfg_to_uses(items: &mut [Item], module_features: &HashMap<CanonicalPath, String>) {
+    for item in items {
+        let Item::Use(use_item) = item else { continue };
+        if let Some(feature) = gated_feature_for_use(&use_item.tree, module_features) {
+            push_cfg_attr(&mut use_item.attrs, &feature);
+        }
+    }
+}
+
+fn gated_feature_for_use(
+    tree: &syn::UseTree,
+    module_features: &HashMap<CanonicalPath, String>,
+) -> Option<String> {
+    gated_feature_for_use_with_prefix(tree, &mut Vec::new(), module_features)
+}
+
+/// Walks the leading path segments of `tree` (skipping `crate`/`self`),
+/// accumulating them in `prefix`, and returns the feature of the first
+/// prefix that names a gated module. Recurses into every branch of a
+/// `UseTree::Group` -- a grouped import like `use crate::{a, b};` gates on
+/// whichever of `a`/`b` actually names a gated module, not just the first
+/// segment of the tree -- and follows a `UseTree::Rename` the same way a
+/// plain `UseTree::Name` is followed, since the gating only depends on what
+/// the path resolves to, not what it's renamed to locally.
+fn gated_feature_for_use_with_prefix(
+    tree: &syn::UseTree,
+    prefix: &mut Vec<String>,
+    module_features: &HashMap<CanonicalPath, String>,
+) -> Option<String> {
+    match tree {
+        syn::UseTree::Path(p) => {
+            let seg = p.ident.to_string();
+            let pushed = seg != "crate" && seg != "self";
+            if pushed {
+                prefix.push(seg);
+            }
+            if let Some(feature) = module_features.get(&prefix.join("::")) {
+                if pushed {
+                    prefix.pop();
+                }
+                return Some(feature.clone());
+            }
+            let found = gated_feature_for_use_with_prefix(&p.tree, prefix, module_features);
+            if pushed {
+                prefix.pop();
+            }
+            found
+        }
+        syn::UseTree::Name(n) => {
+            prefix.push(n.ident.to_string());
+            let feature = module_features.get(&prefix.join("::")).cloned();
+            prefix.pop();
+            feature
+        }
+        syn::UseTree::Rename(r) => {
+            prefix.push(r.ident.to_string());
+            let feature = module_features.get(&prefix.join("::")).cloned();
+            prefix.pop();
+            feature
+        }
+        syn::UseTree::Group(g) => g
+            .items
+            .iter()
+            .find_map(|item| gated_feature_for_use_with_prefix(item, prefix, module_features)),
+        syn::UseTree::Glob(_) => module_features.get(&prefix.join("::")).cloned(),
+    }
+}
+
+fn push_cfg_attr(attrs: &mut Vec<Attribute>, feature: &str) {
+    let attr: Attribute = syn::parse_quote!(#[cfg(feature = #feature)]);
+    let rendered = attr.to_token_stream().to_string();
+    let already_gated = attrs
+        .iter()
+        .any(|existing| existing.to_token_stream().to_string() == rendered);
+    if already_gated {
+        return;
+    }
+    attrs.push(attr);
+}
+
+/// Renders the `[features]` table for the generated `Cargo.toml`, wiring
+/// each feature to the external crate deps its module (plus its support
+/// modules) needs. Features with no external deps still get an entry so the
+/// flag itself exists even if it gates pure in-crate code.
+pub fn render_features_table(plan: &FeaturePlan) -> String {
+    let mut out = String::from("[features]\n");
+    for module in &plan.optional {
+        let deps = module
+            .external_deps
+            .iter()
+            .map(|dep| format!("\"dep:{dep}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+        out.push_str(&format!("{} = [{}]\n", module.feature_name, deps));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Regression test: a grouped import like `use crate::{storage_client,
+    /// other_thing};` used to get zero `#[cfg(...)]` attrs, because
+    /// `gated_feature_for_use` bailed out on the first `UseTree::Group` node
+    /// instead of checking each of its branches.
+    #[test]
+    fn propagates_cfg_through_grouped_use() {
+        let module_features = HashMap::from([("storage_client".to_string(), "storage".to_string())]);
+        let mut items: Vec<Item> = vec![syn::parse_quote!(
+            pub use crate::{storage_client, other_thing};
+        )];
+
+        propagate_cfg_to_uses(&mut items, &module_features);
+
+        let Item::Use(use_item) = &items[0] else {
+            panic!("expected a use item");
+        };
+        assert!(
+            use_item.attrs.iter().any(|attr| attr.path().is_ident("cfg")),
+            "grouped use item referencing a gated module must get a #[cfg(...)] attr"
+        );
+    }
+
+    /// A bare, non-grouped `use crate::storage_client;` must also be gated.
+    #[test]
+    fn propagates_cfg_through_bare_use() {
+        let module_features = HashMap::from([("storage_client".to_string(), "storage".to_string())]);
+        let mut items: Vec<Item> = vec![syn::parse_quote!(
+            pub use crate::storage_client;
+        )];
+
+        propagate_cfg_to_uses(&mut items, &module_features);
+
+        let Item::Use(use_item) = &items[0] else {
+            panic!("expected a use item");
+        };
+        assert!(
+            use_item.attrs.iter().any(|attr| attr.path().is_ident("cfg")),
+            "bare use item referencing a gated module must get a #[cfg(...)] attr"
+        );
+    }
+}
@@ -0,0 +1,18 @@
+//! cargo-subset: flatten a cargo workspace into a single minimized crate.
+//!
+//! The tool inlines a set of workspace crates as modules under one crate
+//! root and then shrinks the result down to what the caller actually needs.
+//! Each pass below operates on the inlined source tree; see the pass-level
+//! docs for what it rewrites.
+
+pub mod features;
+pub mod macros;
+pub mod reachability;
+pub mod reexports;
+pub mod resolver;
+
+pub use features::{gate_module, propagate_cfg_to_uses, render_features_table, FeaturePlan, OptionalModule};
+pub use macros::{rewrite_exported_macros, scoped_reexport, ExportedMacro, MacroRewriteError, RewrittenMacro};
+pub use reachability::{default_roots, prune, CanonicalPath, ImplTarget, PruneReport, SymbolEntry, SymbolTable};
+pub use reexports::{check_named_reexport, expand_glob_reexport, ModuleExports, ReexportError};
+pub use resolver::{InliningPlan, PathResolver};
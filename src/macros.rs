@@ -0,0 +1,250 @@
+//! Collision-free inlining of `#[macro_export]` macros.
+//!
+//! When several workspace crates collapse into one subset crate, every
+//! exported `macro_rules!` lands in the same crate-root macro namespace
+//! (`debug_log!` from one `core` root can collide with `debug_log!` from
+//! another, `log_info!` from `utils`, and so on). This pass renames each
+//! exported macro to a unique internal name, tucks the renamed definition
+//! into a module generated from a hash of its home module, public name, and
+//! token stream, and re-exposes it under its original public name scoped to
+//! the module it used to live in, so callers don't notice the crate
+//! boundary disappeared.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Ident, ItemMacro, ItemMod, ItemUse};
+
+use crate::reachability::CanonicalPath;
+
+/// One exported macro discovered while scanning the inlined tree, tagged
+/// with the module it originally lived at.
+pub struct ExportedMacro {
+    pub home_module: CanonicalPath,
+    pub public_name: String,
+    pub def: ItemMacro,
+}
+
+/// Where a renamed macro ended up: the generated module that now owns the
+/// definition, plus the internal name it was renamed to.
+#[derive(Debug)]
+pub struct RewrittenMacro {
+    pub generated_module: String,
+    pub internal_name: String,
+    pub public_name: String,
+    pub home_module: CanonicalPath,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MacroRewriteError {
+    #[error(
+        "two distinct `#[macro_export]` macros would both be named `{name}` at the crate root \
+         (from `{first}` and `{second}`); rename one of them before subsetting"
+    )]
+    PublicNameCollision {
+        name: String,
+        first: CanonicalPath,
+        second: CanonicalPath,
+    },
+}
+
+/// Rewrites every exported macro in `macros` into a uniquely named, module-
+/// scoped definition plus a `pub use` alias at its original public name.
+/// Deterministic: the generated module name is derived from the macro's own
+/// identity, not from the current pass invocation, so the same input always
+/// yields the same modules and names. Already-rewritten macros are not
+/// recognised: a `___internal_`-named definition passed back in is renamed
+/// and wrapped in a new generated module again.
+pub fn rewrite_exported_macros(
+    macros: Vec<ExportedMacro>,
+) -> Result<(Vec<ItemMod>, Vec<RewrittenMacro>), MacroRewriteError> {
+    // Detect public-name collisions up front: once hoisted to the crate
+    // root, two macros sharing a name is an error, not a pick-one.
+    let mut seen_public_names: HashMap<String, CanonicalPath> = HashMap::new();
+    for mac in &macros {
+        if let Some(first) = seen_public_names.get(&mac.public_name) {
+            if *first != mac.home_module {
+                return Err(MacroRewriteError::PublicNameCollision {
+                    name: mac.public_name.clone(),
+                    first: first.clone(),
+                    second: mac.home_module.clone(),
+                });
+            }
+        } else {
+            seen_public_names.insert(mac.public_name.clone(), mac.home_module.clone());
+        }
+    }
+
+    let mut modules = Vec::new();
+    let mut rewritten = Vec::new();
+
+    for mac in macros {
+        let module_name = generated_module_name(&mac.def, &mac.home_module, &mac.public_name);
+        let internal_name = format!("___internal_{}", mac.public_name);
+        let internal_ident = Ident::new(&internal_name, proc_macro2::Span::call_site());
+        let public_ident = Ident::new(&mac.public_name, proc_macro2::Span::call_site());
+        let module_ident = Ident::new(&module_name, proc_macro2::Span::call_site());
+
+        let mut renamed = mac.def.clone();
+        renamed.ident = Some(internal_ident.clone());
+        rewrite_dollar_crate(&mut renamed, &module_name);
+
+        let module: ItemMod = syn::parse2(quote! {
+            #[doc(hidden)]
+            pub mod #module_ident {
+                #renamed
+                pub use #internal_ident as #public_ident;
+            }
+        })
+        .expect("generated macro module is valid Rust");
+
+        modules.push(module);
+        rewritten.push(RewrittenMacro {
+            generated_module: module_name,
+            internal_name,
+            public_name: mac.public_name,
+            home_module: mac.home_module,
+        });
+    }
+
+    Ok((modules, rewritten))
+}
+
+/// Builds the `pub use` that re-exposes a rewritten macro at the path
+/// callers used to reach it, e.g. `pub use crate::generated::macro_<hash>::debug_log;`
+/// placed inside `core`'s module so `crate::debug_log!` keeps working.
+pub fn scoped_reexport(rewritten: &RewrittenMacro) -> ItemUse {
+    let module_ident = Ident::new(&rewritten.generated_module, proc_macro2::Span::call_site());
+    let name_ident = Ident::new(&rewritten.public_name, proc_macro2::Span::call_site());
+    syn::parse2(quote! { pub use crate::generated::#module_ident::#name_ident; })
+        .expect("generated re-export is valid Rust")
+}
+
+/// Derives a stable, unique module name from the macro's identity -- its
+/// home module, public name, and token stream -- so the same macro always
+/// lands in the same generated module (what makes the pass idempotent)
+/// while two distinct macros never collide, even when their bodies happen
+/// to be textually identical (e.g. two trivial `() => {}` log macros from
+/// different crates).
+fn generated_module_name(def: &ItemMacro, home_module: &str, public_name: &str) -> String {
+    let tokens: TokenStream = def.mac.tokens.clone();
+    let mut hasher = DefaultHasher::new();
+    home_module.hash(&mut hasher);
+    public_name.hash(&mut hasher);
+    tokens.to_string().hash(&mut hasher);
+    format!("macro_{:016x}", hasher.finish())
+}
+
+/// Rewrites `$crate::foo` inside a macro body to `$crate::generated::<module>::foo`,
+/// since after merging, the crate boundary the macro author assumed no
+/// longer separates it from the rest of the subset crate.
+///
+/// `$crate` isn't representable as a `syn::Path` (`$` isn't a valid path
+/// token), so the body is rewritten at the raw token-stream level rather
+/// than through a `syn::visit_mut` pass.
+fn rewrite_dollar_crate(def: &mut ItemMacro, module_name: &str) {
+    def.mac.tokens = rewrite_dollar_crate_tokens(def.mac.tokens.clone(), module_name);
+}
+
+fn rewrite_dollar_crate_tokens(tokens: TokenStream, module_name: &str) -> TokenStream {
+    use proc_macro2::TokenTree;
+
+    let module_ident = Ident::new(module_name, proc_macro2::Span::call_site());
+    let mut out = Vec::new();
+    let mut iter = tokens.into_iter().peekable();
+    while let Some(tt) = iter.next() {
+        match &tt {
+            TokenTree::Punct(p) if p.as_char() == '$' => {
+                if let Some(TokenTree::Ident(id)) = iter.peek() {
+                    if id == "crate" {
+                        iter.next();
+                        out.extend(quote! { $crate::generated::#module_ident });
+                        continue;
+                    }
+                }
+                out.push(tt);
+            }
+            TokenTree::Group(g) => {
+                let inner = rewrite_dollar_crate_tokens(g.stream(), module_name);
+                let mut new_group = proc_macro2::Group::new(g.delimiter(), inner);
+                new_group.set_span(g.span());
+                out.push(TokenTree::Group(new_group));
+            }
+            _ => out.push(tt),
+        }
+    }
+    out.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exported_macro(home_module: &str, public_name: &str, body: proc_macro2::TokenStream) -> ExportedMacro {
+        let name_ident = Ident::new(public_name, proc_macro2::Span::call_site());
+        let def: ItemMacro = syn::parse2(quote! {
+            #[macro_export]
+            macro_rules! #name_ident { #body }
+        })
+        .expect("well-formed macro_rules! item");
+        ExportedMacro {
+            home_module: home_module.to_string(),
+            public_name: public_name.to_string(),
+            def,
+        }
+    }
+
+    /// Two macros with the same public name from different home modules
+    /// must be rejected rather than silently picking one.
+    #[test]
+    fn rejects_colliding_public_names() {
+        let macros = vec![
+            exported_macro("core", "debug_log", quote! { () => {} }),
+            exported_macro("utils", "debug_log", quote! { ($x:expr) => {} }),
+        ];
+
+        let err = rewrite_exported_macros(macros).expect_err("colliding public names must be rejected");
+        assert!(matches!(
+            err,
+            MacroRewriteError::PublicNameCollision { name, .. } if name == "debug_log"
+        ));
+    }
+
+    /// The generated module name is derived from the macro's identity, so
+    /// the same macro always lands in the same module across separate pass
+    /// invocations -- what makes `rewrite_exported_macros` idempotent.
+    #[test]
+    fn generated_module_name_is_stable_for_the_same_macro() {
+        let a = exported_macro("core", "debug_log", quote! { () => {} });
+        let b = exported_macro("core", "debug_log", quote! { () => {} });
+        assert_eq!(
+            generated_module_name(&a.def, &a.home_module, &a.public_name),
+            generated_module_name(&b.def, &b.home_module, &b.public_name)
+        );
+    }
+
+    /// Two distinct macros with textually identical bodies (e.g. two
+    /// trivial `() => {}` log macros from different crates) must not land
+    /// in the same generated module.
+    #[test]
+    fn generated_module_name_differs_for_distinct_macros_with_identical_bodies() {
+        let debug_log = exported_macro("core", "debug_log", quote! { () => {} });
+        let log_info = exported_macro("utils", "log_info", quote! { () => {} });
+        assert_ne!(
+            generated_module_name(&debug_log.def, &debug_log.home_module, &debug_log.public_name),
+            generated_module_name(&log_info.def, &log_info.home_module, &log_info.public_name)
+        );
+    }
+
+    /// `$crate::foo` inside the macro body is rewritten to point through
+    /// the generated module it now lives in.
+    #[test]
+    fn rewrites_dollar_crate_references() {
+        let rewritten = rewrite_dollar_crate_tokens(quote! { $crate::helpers::format_string() }, "macro_abc123");
+        let expected = quote! { $crate::generated::macro_abc123::helpers::format_string() };
+        assert_eq!(rewritten.to_string(), expected.to_string());
+    }
+}
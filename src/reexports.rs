@@ -0,0 +1,277 @@
+//! Expand glob `pub use` re-exports and diagnose re-exports of private items.
+//!
+//! `storage/mod.rs` in the fixtures relies on `pub use format::*;` and
+//! `pub use serializer::*;` to surface `StorageFormat` and `SerializedObject`
+//! from otherwise-private modules. That's fine while `format` and
+//! `serializer` stay separate files, but once everything inlines into one
+//! subset crate a glob re-export is ambiguous and makes later pruning and
+//! macro-collision detection imprecise. This pass resolves each glob against
+//! the real public items of its target module and rewrites it into an
+//! explicit named list, while enforcing the rule rustc itself enforces:
+//! re-exporting an item that isn't `pub` at its definition site is an error.
+
+use std::collections::BTreeMap;
+
+use syn::{Ident, Item, ItemUse, UseTree, Visibility};
+
+/// The public items of one module, keyed by name, along with whether each
+/// one is actually `pub` at its definition site.
+#[derive(Debug, Default)]
+pub struct ModuleExports {
+    /// name -> is the defining item `pub`
+    items: BTreeMap<String, bool>,
+    /// `"Enum::Variant"` -> is the variant reachable through a re-export,
+    /// i.e. is its *enum* `pub`. A variant has no visibility of its own in
+    /// Rust; it inherits the enum's, so re-exporting one from a private
+    /// enum is exactly the same violation as re-exporting a private item.
+    variants: BTreeMap<String, bool>,
+}
+
+impl ModuleExports {
+    /// Scans a module's items and records every named item together with
+    /// its own visibility, so a later glob expansion can tell a genuinely
+    /// public item from one that merely sits inside a module that happens
+    /// to be reachable.
+    pub fn from_file(file: &syn::File) -> Self {
+        let mut items = BTreeMap::new();
+        let mut variants = BTreeMap::new();
+        for item in &file.items {
+            if let Some((name, vis)) = named_item(item) {
+                let is_pub = matches!(vis, Visibility::Public(_));
+                if let Item::Enum(e) = item {
+                    for variant in &e.variants {
+                        variants.insert(format!("{name}::{}", variant.ident), is_pub);
+                    }
+                }
+                items.insert(name, is_pub);
+            }
+        }
+        Self { items, variants }
+    }
+
+    fn public_names(&self) -> Vec<&str> {
+        self.items
+            .iter()
+            .filter(|(_, is_pub)| **is_pub)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReexportError {
+    #[error(
+        "`pub use {module}::{item}` re-exports `{item}`, but it is not `pub` at its definition in \
+         `{module}`; either mark it `pub` there or drop it from the re-export"
+    )]
+    ReexportsPrivateItem { module: String, item: String },
+}
+
+/// If `use_item` is a `pub use <module>::*;` whose target is `exports`,
+/// rewrite it into an explicit named list (`pub use <module>::{A, B};`).
+/// Non-glob, non-public `use` items are returned unchanged. A glob only
+/// ever enumerates `exports`' already-public names, so expanding one never
+/// fails on its own; running `check_named_reexport` on the result is what
+/// catches a *later* hand-edit that narrows the list to a private name.
+pub fn expand_glob_reexport(use_item: &ItemUse, exports: &ModuleExports) -> Result<ItemUse, ReexportError> {
+    if !matches!(use_item.vis, Visibility::Public(_)) {
+        return Ok(use_item.clone());
+    }
+
+    let Some(prefix_tree) = glob_prefix(&use_item.tree) else {
+        return Ok(use_item.clone());
+    };
+
+    // A glob only ever enumerates the module's *public* items, so there's
+    // nothing to diagnose for the glob itself -- the private-item check
+    // matters once a caller hand-narrows the expansion to an explicit list,
+    // which `check_named_reexport` covers.
+    let mut names: Vec<&str> = exports.public_names();
+    names.sort_unstable();
+
+    if names.is_empty() {
+        return Ok(use_item.clone());
+    }
+
+    let idents: Vec<Ident> = names
+        .iter()
+        .map(|name| Ident::new(name, proc_macro2::Span::call_site()))
+        .collect();
+
+    let mut rewritten = use_item.clone();
+    rewritten.tree = rebuild_tree_with_names(prefix_tree, &idents);
+    Ok(rewritten)
+}
+
+/// Diagnoses an already-explicit `pub use module::Item;` (or a
+/// `{A, B}` group) against `exports`, the same rule `expand_glob_reexport`
+/// enforces for globs: every re-exported name must be `pub` where it's
+/// defined.
+pub fn check_named_reexport(
+    use_item: &ItemUse,
+    module_path: &str,
+    exports: &ModuleExports,
+) -> Result<(), ReexportError> {
+    if !matches!(use_item.vis, Visibility::Public(_)) {
+        return Ok(());
+    }
+    for chain in named_leaves(&use_item.tree) {
+        let name = chain.last().expect("named_leaves never yields an empty chain").clone();
+
+        // A two-segment tail like `Status::Active` is a re-exported enum
+        // variant; its visibility isn't its own, it follows `Status`'s, so
+        // check `exports.variants` before falling back to the plain-item
+        // check (which would otherwise look for a top-level item literally
+        // named `Active` and find nothing).
+        if chain.len() >= 2 {
+            let variant_key = format!("{}::{name}", chain[chain.len() - 2]);
+            if let Some(is_pub) = exports.variants.get(&variant_key) {
+                if !is_pub {
+                    return Err(ReexportError::ReexportsPrivateItem {
+                        module: module_path.to_string(),
+                        item: variant_key,
+                    });
+                }
+                continue;
+            }
+        }
+
+        match exports.items.get(&name) {
+            Some(true) | None => {}
+            Some(false) => {
+                return Err(ReexportError::ReexportsPrivateItem {
+                    module: module_path.to_string(),
+                    item: name,
+                })
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Every full ident chain a `use` tree can expand to, e.g.
+/// `module::Status::Active` yields `["module", "Status", "Active"]`. Unlike
+/// a plain leaf-name collector, this keeps the segments leading up to each
+/// leaf so a caller can tell an enum variant's tail (`Status::Active`) apart
+/// from a plain item name.
+fn named_leaves(tree: &UseTree) -> Vec<Vec<String>> {
+    fn walk(tree: &UseTree, prefix: &mut Vec<String>, out: &mut Vec<Vec<String>>) {
+        match tree {
+            UseTree::Path(p) => {
+                prefix.push(p.ident.to_string());
+                walk(&p.tree, prefix, out);
+                prefix.pop();
+            }
+            UseTree::Name(n) => {
+                let mut chain = prefix.clone();
+                chain.push(n.ident.to_string());
+                out.push(chain);
+            }
+            UseTree::Rename(r) => {
+                let mut chain = prefix.clone();
+                chain.push(r.ident.to_string());
+                out.push(chain);
+            }
+            UseTree::Group(g) => {
+                for item in &g.items {
+                    walk(item, prefix, out);
+                }
+            }
+            UseTree::Glob(_) => {}
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(tree, &mut Vec::new(), &mut out);
+    out
+}
+
+/// Returns `tree` itself (the whole tree, `*` included) when it is a chain
+/// of path segments ending in a trailing `*`, or `None` if it isn't a glob.
+fn glob_prefix(tree: &UseTree) -> Option<&UseTree> {
+    match tree {
+        UseTree::Glob(_) => Some(tree),
+        UseTree::Path(p) => glob_prefix(&p.tree).map(|_| tree),
+        _ => None,
+    }
+}
+
+/// Rebuilds a `use` tree, replacing its trailing `*` with an explicit
+/// `{name, name, ...}` group.
+fn rebuild_tree_with_names(tree: &UseTree, names: &[Ident]) -> UseTree {
+    match tree {
+        UseTree::Glob(_) => {
+            let items = names
+                .iter()
+                .cloned()
+                .map(|ident| UseTree::Name(syn::UseName { ident }))
+                .collect();
+            UseTree::Group(syn::UseGroup {
+                brace_token: Default::default(),
+                items,
+            })
+        }
+        UseTree::Path(p) => {
+            let mut rebuilt = p.clone();
+            rebuilt.tree = Box::new(rebuild_tree_with_names(&p.tree, names));
+            UseTree::Path(rebuilt)
+        }
+        other => other.clone(),
+    }
+}
+
+fn named_item(item: &Item) -> Option<(String, &Visibility)> {
+    match item {
+        Item::Struct(i) => Some((i.ident.to_string(), &i.vis)),
+        Item::Enum(i) => Some((i.ident.to_string(), &i.vis)),
+        Item::Fn(i) => Some((i.sig.ident.to_string(), &i.vis)),
+        Item::Trait(i) => Some((i.ident.to_string(), &i.vis)),
+        Item::Const(i) => Some((i.ident.to_string(), &i.vis)),
+        Item::Static(i) => Some((i.ident.to_string(), &i.vis)),
+        Item::Type(i) => Some((i.ident.to_string(), &i.vis)),
+        Item::Mod(i) => Some((i.ident.to_string(), &i.vis)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Regression test: `Status` is a private enum (no `pub` on the `enum`
+    /// itself), so `pub use status_mod::Status::Active;` re-exports a
+    /// variant that's no more reachable from outside the module than
+    /// `Status` itself is. `ModuleExports` used to only record the enum
+    /// item, never its variants, so this slipped past `check_named_reexport`
+    /// silently.
+    #[test]
+    fn rejects_reexported_variant_of_private_enum() {
+        let file: syn::File = syn::parse_str(
+            "enum Status { Active, Retired }\n",
+        )
+        .expect("inline source parses");
+        let exports = ModuleExports::from_file(&file);
+
+        let use_item: ItemUse = syn::parse_quote!(pub use status_mod::Status::Active;);
+        let err = check_named_reexport(&use_item, "status_mod", &exports)
+            .expect_err("re-exporting a variant of a private enum must be rejected");
+
+        assert!(matches!(
+            err,
+            ReexportError::ReexportsPrivateItem { item, .. } if item == "Status::Active"
+        ));
+    }
+
+    /// A variant of a `pub` enum re-exports cleanly.
+    #[test]
+    fn allows_reexported_variant_of_public_enum() {
+        let file: syn::File = syn::parse_str(
+            "pub enum Status { Active, Retired }\n",
+        )
+        .expect("inline source parses");
+        let exports = ModuleExports::from_file(&file);
+
+        let use_item: ItemUse = syn::parse_quote!(pub use status_mod::Status::Active;);
+        check_named_reexport(&use_item, "status_mod", &exports).expect("public variant re-exports cleanly");
+    }
+}
@@ -0,0 +1,275 @@
+//! Unified workspace-path canonicalizer.
+//!
+//! The same logical reference shows up spelled several incompatible ways
+//! across the fixtures: `use crate::utils::helpers::format_string;`, bare
+//! `use utils::helpers::format_string;`, `use core::types::Item;`,
+//! `use core::Config;`, self-referential `pub use config::Config;` vs
+//! `pub use crate::config::Config;`, and a bare relative
+//! `pub use http_client::HttpClient;` that needs a `super::` prefix once
+//! its surrounding code is relocated. [`reachability`](crate::reachability)
+//! and [`macros`](crate::macros) used to each grow their own ad-hoc path
+//! handling; this module is the single shared backend they (and any future
+//! pass) should query instead, so canonicalization logic lives in one
+//! place.
+
+use std::collections::{HashMap, HashSet};
+
+use syn::punctuated::Punctuated;
+use syn::{Path, PathSegment};
+
+use crate::reachability::CanonicalPath;
+
+/// Describes how the inlining pass folds each workspace crate into the
+/// subset crate.
+#[derive(Debug, Default)]
+pub struct InliningPlan {
+    /// Original workspace crate name -> the module path it becomes under
+    /// the subset root (e.g. `"core"` -> `"core"`, `"client"` -> `""` if a
+    /// crate is promoted to be the subset root itself).
+    pub crate_modules: HashMap<String, CanonicalPath>,
+    /// Crates whose root-level (non-`mod`) items were relocated into a
+    /// generated `<module>::root` submodule, because the output layout
+    /// keeps a module's child-module declarations and its own loose items
+    /// in separate generated files. A bare reference written from inside
+    /// that relocated root back to a sibling child module needs an extra
+    /// `super::` to still find it.
+    pub root_relocated: HashSet<String>,
+}
+
+impl InliningPlan {
+    /// The workspace crate `module` belongs to, found by longest matching
+    /// prefix over `crate_modules`. A crate promoted to the subset root
+    /// (`crate_modules[name] == ""`) matches every module not claimed by a
+    /// more specific, non-empty prefix -- an empty path means "the root
+    /// itself", not the literal string `"::"`.
+    pub fn owning_crate(&self, module: &str) -> Option<&str> {
+        self.crate_modules
+            .iter()
+            .filter(|(_, path)| {
+                path.is_empty() || module == path.as_str() || module.starts_with(&format!("{path}::"))
+            })
+            .max_by_key(|(_, path)| path.len())
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+/// The shared path-rewriting backend. Holds the [`InliningPlan`] and
+/// canonicalizes a `use`/`pub use` path written inside `current_module` to
+/// where it needs to point after merging.
+pub struct PathResolver<'a> {
+    plan: &'a InliningPlan,
+}
+
+impl<'a> PathResolver<'a> {
+    pub fn new(plan: &'a InliningPlan) -> Self {
+        Self { plan }
+    }
+
+    /// Resolves `path`, written inside `current_module`, to the absolute,
+    /// fully-qualified canonical path it points at after merging (e.g.
+    /// `"core::types::Item"`). This is what [`reachability`](crate::reachability)
+    /// and any other pass that needs a stable graph key should call; use
+    /// [`canonicalize`](Self::canonicalize) instead when rewriting the
+    /// source text itself.
+    pub fn resolve_absolute(&self, current_module: &str, path: &Path) -> CanonicalPath {
+        let head = path.segments[0].ident.to_string();
+        let rest: Vec<PathSegment> = path.segments.iter().skip(1).cloned().collect();
+
+        if head == "super" {
+            // `super::` paths aren't rewritten (the merge preserves each
+            // crate's own module depth), so resolve them relative to the
+            // textual parent of `current_module`.
+            let parent = current_module.rsplit_once("::").map(|(p, _)| p).unwrap_or("");
+            return join(parent, &rest);
+        }
+
+        if head == "self" {
+            return join(current_module, &rest);
+        }
+
+        if head == "crate" {
+            let owner = self
+                .plan
+                .owning_crate(current_module)
+                .expect("current_module must belong to a crate in the plan");
+            return join(&self.plan.crate_modules[owner], &rest);
+        }
+
+        if let Some(target_module) = self.plan.crate_modules.get(&head) {
+            // `use core::types::Item;` / `use utils::helpers;` -- a bare
+            // reference to another workspace crate.
+            return join(target_module, &rest);
+        }
+
+        // A bare name that isn't a known workspace crate: a reference to a
+        // sibling item declared in the same module, e.g.
+        // `http_client::HttpClient` next to `mod http_client;`, resolved as
+        // a child of `current_module`. The `root_relocated` layout is not
+        // consulted here; only `canonicalize` adds the extra `super::` for it.
+        join(current_module, path.segments.iter().cloned().collect::<Vec<_>>().as_slice())
+    }
+
+    /// Canonicalizes `path`, written inside `current_module`, to its
+    /// post-merge form: the shortest unambiguous rewrite of the source text
+    /// itself (bare when self-referential, `super::`-prefixed when the
+    /// reference crosses a relocated root, `crate::`-rooted otherwise).
+    pub fn canonicalize(&self, current_module: &str, path: &Path) -> Path {
+        let head = path.segments[0].ident.to_string();
+        let rest: Vec<PathSegment> = path.segments.iter().skip(1).cloned().collect();
+
+        if head == "super" {
+            return path.clone();
+        }
+
+        if head == "self" {
+            return self.render(current_module, current_module, &rest);
+        }
+
+        if head == "crate" {
+            let owner = self
+                .plan
+                .owning_crate(current_module)
+                .expect("current_module must belong to a crate in the plan");
+            let owner_module = self.plan.crate_modules[owner].clone();
+            return self.render(current_module, &owner_module, &rest);
+        }
+
+        if let Some(target_module) = self.plan.crate_modules.get(&head) {
+            // Must always be emitted fully qualified: `core` may also be
+            // the name of a real external crate (it's the name of
+            // libcore!), so leaving it bare after merging would silently
+            // start resolving to the wrong thing instead of failing to
+            // compile.
+            return self.render(current_module, target_module, &rest);
+        }
+
+        let owner = self.plan.owning_crate(current_module);
+        if let Some(owner) = owner {
+            let owner_module = &self.plan.crate_modules[owner];
+            let relocated_root = format!("{owner_module}::root");
+            if self.plan.root_relocated.contains(owner) && current_module == relocated_root {
+                let mut segments = Punctuated::new();
+                segments.push(ident_segment("super"));
+                segments.push(ident_segment(&head));
+                for seg in &rest {
+                    segments.push(seg.clone());
+                }
+                return Path {
+                    leading_colon: None,
+                    segments,
+                };
+            }
+        }
+
+        let mut full_rest = vec![path.segments[0].clone()];
+        full_rest.extend(rest);
+        self.render(current_module, current_module, &full_rest)
+    }
+
+    /// Renders a reference to `base::<rest>`, written inside
+    /// `current_module`, in its shortest unambiguous form: a bare relative
+    /// path when `base` is exactly `current_module` (a same-crate
+    /// self-reference), otherwise a fully qualified `crate::`-rooted path.
+    fn render(&self, current_module: &str, base: &str, rest: &[PathSegment]) -> Path {
+        if base == current_module {
+            if rest.is_empty() {
+                // referencing the module itself, e.g. `use self;` -- leave
+                // untouched, there's nothing to canonicalize.
+                return parse_path("self");
+            }
+            let mut segments = Punctuated::new();
+            for seg in rest {
+                segments.push(seg.clone());
+            }
+            return Path {
+                leading_colon: None,
+                segments,
+            };
+        }
+
+        let mut segments = Punctuated::new();
+        segments.push(ident_segment("crate"));
+        if !base.is_empty() {
+            for part in base.split("::") {
+                segments.push(ident_segment(part));
+            }
+        }
+        for seg in rest {
+            segments.push(seg.clone());
+        }
+        Path {
+            leading_colon: None,
+            segments,
+        }
+    }
+}
+
+fn join(base: &str, rest: &[PathSegment]) -> CanonicalPath {
+    let rest_str = rest
+        .iter()
+        .map(|seg| seg.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::");
+    if base.is_empty() {
+        rest_str
+    } else if rest_str.is_empty() {
+        base.to_string()
+    } else {
+        format!("{base}::{rest_str}")
+    }
+}
+
+fn ident_segment(name: &str) -> PathSegment {
+    PathSegment::from(syn::Ident::new(name, proc_macro2::Span::call_site()))
+}
+
+fn parse_path(s: &str) -> Path {
+    syn::parse_str(s).expect("canonicalized path is a valid Rust path")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Regression test for resolving a path from a module belonging to a
+    /// crate that was promoted to the subset root (`path == ""`).
+    /// `tests/fixtures/workspace/client/src/http_client.rs` is exactly this
+    /// layout: `client` becomes the subset root, and `http_client.rs`
+    /// (module `"http_client"`) references `crate::interface::{Provider,
+    /// Client}`. `owning_crate` used to degenerate to matching the literal
+    /// string `"::"` for an empty path and never find `client` here.
+    #[test]
+    fn owning_crate_matches_root_promoted_crate() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace/client/src/http_client.rs");
+        let source = std::fs::read_to_string(path).expect("fixture file exists");
+        let file = syn::parse_file(&source).expect("fixture file parses");
+        let use_item = file
+            .items
+            .iter()
+            .find_map(|item| match item {
+                syn::Item::Use(u) => Some(u),
+                _ => None,
+            })
+            .expect("fixture has a use item");
+        let syn::UseTree::Path(ref use_path) = use_item.tree else {
+            panic!("expected `use crate::interface::{{..}}`");
+        };
+        let import_path: Path = syn::parse_str(&format!("{}::{}", use_path.ident, "interface::Provider"))
+            .expect("well-formed path");
+
+        let plan = InliningPlan {
+            crate_modules: HashMap::from([("client".to_string(), "".to_string())]),
+            root_relocated: Default::default(),
+        };
+
+        assert_eq!(plan.owning_crate("http_client"), Some("client"));
+
+        let resolver = PathResolver::new(&plan);
+        assert_eq!(
+            resolver.resolve_absolute("http_client", &import_path),
+            "interface::Provider"
+        );
+    }
+}